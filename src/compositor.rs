@@ -0,0 +1,78 @@
+//! A Cursive/Helix-style compositor: a stack of [`Component`] layers rendered
+//! bottom-to-top into a single [`RenderBuffer`] and flushed once per frame.
+//!
+//! Instead of each widget seizing the thread with its own input loop, the main
+//! loop feeds one event at a time to the topmost layer. A layer reports what it
+//! did through [`EventResult`], which also lets it grow or shrink the stack —
+//! opening a sub-menu pushes a layer, dismissing it pops one — so nothing has
+//! to recurse and the layers underneath stay live and redrawable.
+
+use crate::layout::Rect;
+use crate::render::RenderBuffer;
+
+use termion::event::Event;
+
+/// What a [`Component`] did with an event, and how the stack should change.
+pub enum EventResult {
+    /// The event was handled; stop routing it.
+    Consumed,
+    /// The event did not apply to this layer.
+    Ignored,
+    /// Handled, and a new layer should be pushed on top.
+    PushLayer(Box<dyn Component>),
+    /// Handled, and this layer should be removed from the stack.
+    Pop,
+}
+
+/// A renderable, event-handling layer in the [`Compositor`] stack.
+pub trait Component {
+    /// Draw this layer into `buf` within `area`. Lower layers have already been
+    /// drawn, so a transparent component can leave their cells untouched.
+    fn render(&self, buf: &mut RenderBuffer, area: Rect);
+
+    /// Handle a single input event, reporting the result to the compositor.
+    fn handle_event(&mut self, event: Event) -> EventResult;
+}
+
+/// A bottom-to-top stack of [`Component`]s.
+#[derive(Default)]
+pub struct Compositor {
+    layers: Vec<Box<dyn Component>>,
+}
+
+impl Compositor {
+    pub fn new() -> Compositor {
+        Compositor { layers: Vec::new() }
+    }
+
+    /// Push a layer onto the top of the stack.
+    pub fn push(&mut self, component: Box<dyn Component>) {
+        self.layers.push(component);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Route an event to the topmost layer and apply its [`EventResult`].
+    /// Returns `true` if the event was consumed (including stack changes),
+    /// `false` if the top layer ignored it.
+    pub fn handle_event(&mut self, event: Event) -> bool {
+        match self.layers.last_mut() {
+            Some(top) => match top.handle_event(event) {
+                EventResult::Consumed => true,
+                EventResult::Ignored => false,
+                EventResult::Pop => { self.layers.pop(); true },
+                EventResult::PushLayer(layer) => { self.layers.push(layer); true },
+            },
+            None => false,
+        }
+    }
+
+    /// Render every layer bottom-to-top into `buf`.
+    pub fn render(&self, buf: &mut RenderBuffer, area: Rect) {
+        for layer in &self.layers {
+            layer.render(buf, area);
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! A small constraint-based layout engine for carving a parent rectangle into
+//! child regions. Callers describe a split as a [`Direction`] plus a list of
+//! [`Constraint`]s and receive back a `Vec<Rect>` that exactly tiles the parent
+//! with no gap or overlap, so menu bars, status lines, gutters, and split
+//! editor panes can be positioned declaratively instead of by ad-hoc arithmetic.
+
+/// A rectangular region in terminal (column, row) space.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Rect {
+    pub fn new(x: u16, y: u16, width: u16, height: u16) -> Rect {
+        Rect { x, y, width, height }
+    }
+
+    /// The length of this rect along `direction` — the axis a split divides.
+    fn length(&self, direction: Direction) -> u16 {
+        match direction {
+            Direction::Horizontal => self.width,
+            Direction::Vertical => self.height,
+        }
+    }
+}
+
+/// The axis a [`Layout`] divides the parent along.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Direction {
+    Horizontal,
+    Vertical,
+}
+
+/// A single child's sizing rule. `Fixed`, `Percent`, and `Ratio` resolve to a
+/// concrete size against the parent dimension; `Min` is flexible and absorbs
+/// whatever space is left over (never shrinking below its floor).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Constraint {
+    Fixed(u16),
+    Percent(u16),
+    Min(u16),
+    Ratio(u32, u32),
+}
+
+/// A described split: a direction and one constraint per child region.
+pub struct Layout {
+    direction: Direction,
+    constraints: Vec<Constraint>,
+}
+
+impl Layout {
+    pub fn new(direction: Direction, constraints: Vec<Constraint>) -> Layout {
+        Layout { direction, constraints }
+    }
+
+    /// Solve the constraints against `area`, returning one [`Rect`] per
+    /// constraint. The regions run back-to-back along the layout's direction
+    /// and span the full extent across it.
+    pub fn split(&self, area: Rect) -> Vec<Rect> {
+        let total = area.length(self.direction);
+        let n = self.constraints.len();
+
+        // First pass: resolve the fixed-ish constraints and note the flexible
+        // (`Min`) children, which share whatever space remains afterwards.
+        let mut sizes = vec![0u16; n];
+        let mut flexible = Vec::new();
+        let mut used = 0u16;
+        for (i, c) in self.constraints.iter().enumerate() {
+            let size = match *c {
+                Constraint::Fixed(v) => v,
+                Constraint::Percent(p) => (p as u32 * total as u32 / 100) as u16,
+                Constraint::Ratio(num, den) => (num as u64 * total as u64 / den as u64) as u16,
+                Constraint::Min(m) => { flexible.push(i); m }
+            };
+            sizes[i] = size;
+            used = used.saturating_add(size);
+        }
+
+        // Second pass: hand the leftover space to the flexible children, with
+        // any rounding remainder landing on the last one so the regions tile
+        // the parent exactly. With no flexible child the remainder falls to the
+        // final region instead.
+        let mut remaining = total.saturating_sub(used);
+        if !flexible.is_empty() {
+            let share = remaining / flexible.len() as u16;
+            for &i in &flexible {
+                sizes[i] += share;
+            }
+            remaining -= share * flexible.len() as u16;
+            if let Some(&last) = flexible.last() {
+                sizes[last] += remaining;
+            }
+        } else if n > 0 {
+            sizes[n - 1] += remaining;
+        }
+
+        // Lay the sized regions out head-to-tail along the split axis, clamping
+        // to the parent's far edge so that over-committed constraints (e.g. two
+        // `Fixed(60)` in a 100-wide area) never produce rects that overflow the
+        // parent — later regions are simply truncated, then collapse to zero.
+        let start = match self.direction {
+            Direction::Horizontal => area.x,
+            Direction::Vertical => area.y,
+        };
+        let end = start + total;
+        let mut rects = Vec::with_capacity(n);
+        let mut offset = start;
+        for &size in &sizes {
+            let size = size.min(end - offset);
+            let rect = match self.direction {
+                Direction::Horizontal => Rect::new(offset, area.y, size, area.height),
+                Direction::Vertical => Rect::new(area.x, offset, area.width, size),
+            };
+            rects.push(rect);
+            offset += size;
+        }
+        rects
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn widths(rects: &[Rect]) -> Vec<u16> {
+        rects.iter().map(|r| r.width).collect()
+    }
+
+    #[test]
+    fn fixed_and_flexible_tile_exactly() {
+        let rects = Layout::new(
+            Direction::Horizontal,
+            vec![Constraint::Fixed(10), Constraint::Min(0), Constraint::Fixed(7)],
+        )
+        .split(Rect::new(0, 0, 100, 1));
+
+        assert_eq!(widths(&rects), vec![10, 83, 7]);
+        // Regions run back-to-back and cover the whole parent.
+        assert_eq!(rects[1].x, 10);
+        assert_eq!(rects[2].x, 93);
+        assert_eq!(rects.iter().map(|r| r.width).sum::<u16>(), 100);
+    }
+
+    #[test]
+    fn percent_resolves_against_parent_and_remainder_goes_last() {
+        let rects = Layout::new(
+            Direction::Vertical,
+            vec![Constraint::Percent(30), Constraint::Min(1), Constraint::Min(1)],
+        )
+        .split(Rect::new(0, 0, 1, 101));
+
+        let heights: Vec<u16> = rects.iter().map(|r| r.height).collect();
+        // 30% of 101 rounds down to 30; the other 71 split across two flexible
+        // children as 35 and 36, the odd row going to the last one.
+        assert_eq!(heights, vec![30, 35, 36]);
+        assert_eq!(heights.iter().sum::<u16>(), 101);
+    }
+
+    #[test]
+    fn over_committed_fixed_does_not_overflow_parent() {
+        let rects = Layout::new(
+            Direction::Horizontal,
+            vec![Constraint::Fixed(60), Constraint::Fixed(60)],
+        )
+        .split(Rect::new(0, 0, 100, 1));
+
+        // The second region is truncated so nothing spills past the parent.
+        assert_eq!(widths(&rects), vec![60, 40]);
+        assert!(rects.iter().all(|r| r.x + r.width <= 100));
+    }
+}
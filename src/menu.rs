@@ -1,7 +1,11 @@
-use termion::{*, input::TermRead, event::Key};
+use termion::{*, event::{Event, Key, MouseEvent, MouseButton}};
 
 use std::io::Write;
 
+use crate::compositor::{Component, EventResult};
+use crate::layout::{Constraint, Direction, Layout, Rect};
+use crate::render::{Attrs, Color, Draw, RenderBuffer};
+
 /// A horizontal (x by 1) list of menus. Think 'File  Edit  Selection  View ...'
 pub struct MenuBar {
     pub selection_index: usize,
@@ -11,11 +15,12 @@ pub struct MenuBar {
 /// A vertical menu of possible actions, which one could possibly expand a sub-menu.
 ///
 /// These are usually rendered by the MenuBar when a menu item was selected.
+#[derive(Clone)]
 pub struct Menu {
     pub children: Vec<(String, MenuAction)>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Action {
     // Hardcoded menus //
 
@@ -29,12 +34,30 @@ pub enum Action {
     Scripted,
 }
 
+#[derive(Clone)]
 pub enum MenuAction {
     Separator,
     Action(Action),
     SubMenu(Menu),
 }
 
+/// The outcome of routing a `MouseEvent` into an open [`Menu`].
+pub enum MenuMouse<'a> {
+    /// The pointer moved over a selectable row; `selection_index` was updated.
+    Hovered,
+    /// A row was clicked; activate this action or expand this sub-menu.
+    Activated(&'a MenuAction),
+    /// The click fell outside the menu, which should now be dismissed.
+    Dismiss,
+    /// The event did not concern this menu.
+    Ignored,
+}
+
+/// The displayed width of a menu name, i.e. without the `_` shortcut marker.
+fn display_len(name: &str) -> usize {
+    name.chars().count() - if name.contains('_') { 1 } else { 0 }
+}
+
 fn get_menu_shortcut_from_name(name: &str) -> char {
     let mut chars = name.chars();
     while let Some(c) = chars.next() {
@@ -91,15 +114,47 @@ impl MenuBar {
         }
     }
 
+    /// The column where each menu's name begins, derived declaratively from a
+    /// horizontal [`Layout`]: left-packed items, a flexible spacer, then `_Help`
+    /// pinned to the far right. This replaces the old hand-computed offsets and
+    /// the `terminal_size() - 7` special case.
+    fn menu_origins(&self) -> Vec<u16> {
+        let width = terminal_size().unwrap().0;
+        let help = self.menus.iter().position(|(name, _)| name == "_Help");
+
+        // Each item is rendered as " name " — the display width plus its two
+        // padding spaces. `order` maps each layout region back to its menu
+        // index (or `None` for the spacer that pushes Help to the right).
+        let mut order: Vec<Option<usize>> = Vec::new();
+        let mut constraints = Vec::new();
+        for (i, (name, _)) in self.menus.iter().enumerate() {
+            if Some(i) == help { continue; }
+            order.push(Some(i));
+            constraints.push(Constraint::Fixed(display_len(name) as u16 + 2));
+        }
+        if let Some(h) = help {
+            order.push(None);
+            constraints.push(Constraint::Min(0));
+            order.push(Some(h));
+            constraints.push(Constraint::Fixed(display_len(&self.menus[h].0) as u16 + 2));
+        }
+
+        let rects = Layout::new(Direction::Horizontal, constraints)
+            .split(Rect::new(1, 1, width, 1));
+
+        // The name starts one column past the region's leading padding space.
+        let mut origins = vec![0u16; self.menus.len()];
+        for (region, slot) in order.iter().enumerate() {
+            if let Some(i) = slot {
+                origins[*i] = rects[region].x + 1;
+            }
+        }
+        origins
+    }
+
     fn get_origin_x_of_menu(&self, idx: usize) -> u16 {
         assert!(!self.menus.is_empty());
-        if self.menus[idx].0 == "_Help" { // Annoying, Help is planted on the far right for style
-            terminal_size().unwrap().0 - 7
-        } else {
-            (1 + self.menus.iter().take(idx).map(|(name, _)| name.len()).sum::<usize>() // We have a single space before menus are listed off
-            + (idx + 1) * 1) // For spaces before and after names (number of items * 1)
-            as u16
-        }
+        self.menu_origins()[idx]
     }
 
     /// Returns a menu and the origin X offset of the menu, for rendering the menu in the correct position.
@@ -128,94 +183,31 @@ impl MenuBar {
         }
         None
     }
-}
-
-impl Menu {
-    pub fn render<S: Write>(&self, s: &mut S, origin: (u16, u16), selection_index: usize) {
-        let width = self.get_menu_width();
-
-        // Render background box
-        crate::util::draw_rectangle(s, &color::White, origin, (width, self.children.len() + 2));
-
-        // Render box outline
-        crate::util::draw_thin_unfilled_rectangle(s, &color::Black, &color::White, origin, (width, self.children.len() + 2));
-
-        for (i, (name, a)) in self.children.iter().enumerate() {
-            // goto, print name ; note the spaces before and after name (padding)
-            write!(s, "{}{}{}", cursor::Goto(origin.0 + 1, origin.1 + 1 + i as u16),
-                // Background of a selected item is brighter than others
-                if i == selection_index { format!("{}{}", color::Bg(color::Black), color::Fg(color::White)) } else { format!("{}{}", color::Bg(color::White), color::Fg(color::Black)) },
-                match a {
-                    MenuAction::Separator => "─".repeat(width - 2), // width - 2 is the maximum name length
-                    _ => {
-                        let mut formatted = String::new();
-                        let mut chars = name.chars();
-                        while let Some(c) = chars.next() {
-                            if c == '_' {
-                                formatted.push_str(&format!(
-                                    "{}{}{}",
-                                    color::Fg(color::LightWhite),
-                                    chars.next().unwrap(),
-                                    if i == selection_index { format!("{}", color::Fg(color::White)) } else { format!("{}", color::Fg(color::Black)) }
-                                ));
-                            } else {
-                                formatted.push(c);
-                            }
-                        }
-                        formatted.push_str(&" ".repeat(width - 2 - if name.contains("_") { name.len() - 1 } else { name.len() } ));
-                        formatted
-                    }
-                },
-            ).unwrap();
-        }
-    }
-
-    /// Take over the current thread and handle the menu's input. This causes recursion when expanding
-    /// sub-menus.
-    pub fn take_over<S: Write>(&self, s: &mut S, x_offset: u16) -> Option<&Action> {
-        let mut selection_index = 0usize;
-        loop {
-            self.render(s, (x_offset, 2), selection_index);
-
-            s.flush().unwrap();
-
-            // All of the input code for a graphical menu.
-            if let Some(k) = std::io::stdin().keys().next() {
-                match k.unwrap() {
-                    Key::Up => selection_index = self.previous(selection_index),
-                    Key::Down => selection_index = self.next(selection_index),
-
-                    // Activate an action or sub-menu expansion using the enter key.
-                    Key::Char('\n') => match &self.children[selection_index].1 {
-                        MenuAction::Separator => unreachable!(),
-                        MenuAction::Action(action) => return Some(action),
-                        MenuAction::SubMenu(menu) => match menu.take_over(s, x_offset + self.get_menu_width() as u16) {
-                            Some(action) => return Some(action),
-                            _ => {} // We don't want to close this menu if they exited out of the sub-child one.
-                        },
-                    },
-
-                    // Activate an action or sub-menu expansion using a shortcut.
-                    Key::Char(c) => if let Some(menu_action) = self.maybe_handle_key_press(c) {
-                        match menu_action {
-                            MenuAction::Separator => unreachable!(),
-                            MenuAction::Action(action) => return Some(action),
-                            MenuAction::SubMenu(menu) => match menu.take_over(s, x_offset + self.get_menu_width() as u16) {
-                                Some(action) => return Some(action),
-                                _ => {} // We don't want to close the menu... same as above ^
-                            }
-                        }
-                    } else {
-                        break; // For now, when you press an unknown key it will close the menu.
-                    },
 
-                    _ => break,
+    /// Returns a menu and its origin X offset when a left click lands on a
+    /// menu-bar item's rendered span on the bar's own `row` (the `origin.1`
+    /// passed to [`Self::render`]), mirroring [`Self::maybe_handle_key_press`]
+    /// so the caller can route `MouseEvent`s in the same way. Clicks on any
+    /// other row — e.g. in the editor body below an item — are ignored.
+    pub fn maybe_handle_mouse(&mut self, mouse: MouseEvent, row: u16) -> Option<(&Menu, u16)> {
+        if let MouseEvent::Press(MouseButton::Left, x, y) = mouse {
+            if y != row {
+                return None;
+            }
+            for i in 0..self.menus.len() {
+                let origin = self.get_origin_x_of_menu(i);
+                let width = display_len(&self.menus[i].0) as u16;
+                if x >= origin && x < origin + width {
+                    self.selection_index = i;
+                    return Some((&self.menus[i].1, origin));
                 }
             }
         }
         None
     }
+}
 
+impl Menu {
     fn previous(&self, mut selection_index: usize) -> usize {
         // Perform reverse wrapping
         if selection_index as isize - 1 < 0 { selection_index = self.children.len()-1; } else { selection_index -= 1; }
@@ -243,19 +235,198 @@ impl Menu {
         )
     }
 
-    /// Returns `true` if the key press was correctly handled,
-    /// or `false` if the key could not be handled (or was not recognized).
-    fn maybe_handle_key_press(&self, key: char) -> Option<&MenuAction> {
+    /// The index of the non-separator child whose shortcut letter is `key`, if
+    /// any. Borrows nothing from the menu, so callers can act on the result
+    /// while mutating the menu.
+    fn shortcut_index(&self, key: char) -> Option<usize> {
         let key = key.to_lowercase().next().unwrap();
-        for (c, menu) in self
-            .children
-            .iter()
-            .filter_map(|(s, a)| match a { MenuAction::Separator=>None, _=>Some((get_menu_shortcut_from_name(s), a)) }) // Ignore separators, too
-        {
-            if c.to_lowercase().next().unwrap() == key {
-                return Some(menu);
+        self.children.iter().enumerate().find_map(|(i, (s, a))| match a {
+            MenuAction::Separator => None,
+            _ => if get_menu_shortcut_from_name(s).to_lowercase().next().unwrap() == key {
+                Some(i)
+            } else {
+                None
+            },
+        })
+    }
+
+    /// Route a `MouseEvent` into this menu rendered at `origin`. A left click or
+    /// drag over a non-separator row moves `selection_index` onto it (hover),
+    /// a click additionally activates its action or sub-menu, and a click
+    /// outside the menu box requests dismissal.
+    pub fn maybe_handle_mouse(&self, mouse: MouseEvent, origin: (u16, u16), selection_index: &mut usize) -> MenuMouse<'_> {
+        let (mx, my, pressed) = match mouse {
+            MouseEvent::Press(MouseButton::Left, x, y) => (x, y, true),
+            MouseEvent::Hold(x, y) => (x, y, false),
+            _ => return MenuMouse::Ignored,
+        };
+
+        // The rendered box spans the outline on every side (see `render`).
+        let width = self.get_menu_width() as u16;
+        let left = origin.0;
+        let right = origin.0 + width - 1;
+        let top = origin.1;
+        let bottom = origin.1 + self.children.len() as u16 + 1;
+        if mx < left || mx > right || my < top || my > bottom {
+            return if pressed { MenuMouse::Dismiss } else { MenuMouse::Ignored };
+        }
+
+        // Rows origin.1 + 1 ..= origin.1 + children.len() hold the items.
+        if my >= origin.1 + 1 && my <= origin.1 + self.children.len() as u16 {
+            let i = (my - origin.1 - 1) as usize;
+            if let MenuAction::Separator = self.children[i].1 {
+                return MenuMouse::Ignored; // separators aren't selectable
             }
+            *selection_index = i;
+            return if pressed {
+                MenuMouse::Activated(&self.children[i].1)
+            } else {
+                MenuMouse::Hovered
+            };
         }
-        None
+
+        MenuMouse::Ignored
+    }
+}
+
+/// A [`Menu`] wrapped as a compositor [`Component`], holding the transient
+/// selection and screen origin for an open menu. Opening a sub-menu pushes
+/// another `MenuLayer`; dismissing pops it.
+pub struct MenuLayer {
+    menu: Menu,
+    origin: (u16, u16),
+    selection_index: usize,
+    /// Set to the chosen action once a leaf item is activated, for the owning
+    /// editor to drain after the layer pops.
+    pub activated: Option<Action>,
+}
+
+impl MenuLayer {
+    pub fn new(menu: Menu, origin: (u16, u16)) -> MenuLayer {
+        MenuLayer { menu, origin, selection_index: 0, activated: None }
+    }
+
+    /// Activate the child at `index`: a leaf action is recorded and the layer
+    /// pops, a sub-menu is pushed as a new layer beside this one, and a
+    /// separator is a no-op.
+    fn activate(&mut self, index: usize) -> EventResult {
+        // Decide first, so the borrow of `self.menu` is released before we
+        // mutate `self` or hand ownership to a new layer.
+        enum Act { Separator, Leaf(Action), Sub(Menu, u16) }
+        let act = match &self.menu.children[index].1 {
+            MenuAction::Separator => Act::Separator,
+            MenuAction::Action(action) => Act::Leaf(action.clone()),
+            MenuAction::SubMenu(menu) => Act::Sub(menu.clone(), self.menu.get_menu_width() as u16),
+        };
+        match act {
+            Act::Separator => EventResult::Consumed,
+            Act::Leaf(action) => {
+                self.activated = Some(action);
+                EventResult::Pop
+            },
+            Act::Sub(menu, width) => {
+                let origin = (self.origin.0 + width, self.origin.1);
+                EventResult::PushLayer(Box::new(MenuLayer::new(menu, origin)))
+            },
+        }
+    }
+}
+
+impl Component for MenuLayer {
+    fn render(&self, buf: &mut RenderBuffer, _area: Rect) {
+        let width = self.menu.get_menu_width();
+        let (ox, oy) = (self.origin.0 as usize, self.origin.1 as usize);
+
+        // Fill the menu box, then lay out one row per child.
+        buf.set_fg(Color::Black);
+        buf.set_bg(Color::White);
+        buf.draw((ox, oy), Draw::Rect(width, self.menu.children.len() + 2));
+
+        for (i, (name, a)) in self.menu.children.iter().enumerate() {
+            let row = oy + 1 + i;
+            // Reverse-video the selected row rather than abusing foreground.
+            if i == self.selection_index {
+                buf.set_attrs(Attrs::REVERSE);
+            } else {
+                buf.clear_attrs();
+            }
+            match a {
+                MenuAction::Separator => {
+                    let rule = "─".repeat(width - 2);
+                    buf.draw((ox + 1, row), Draw::Text(&rule));
+                },
+                _ => {
+                    let label = name.replace('_', "");
+                    buf.draw((ox + 1, row), Draw::Text(&label));
+                },
+            }
+        }
+        buf.clear_attrs();
+    }
+
+    fn handle_event(&mut self, event: Event) -> EventResult {
+        match event {
+            Event::Key(Key::Up) => {
+                self.selection_index = self.menu.previous(self.selection_index);
+                EventResult::Consumed
+            },
+            Event::Key(Key::Down) => {
+                self.selection_index = self.menu.next(self.selection_index);
+                EventResult::Consumed
+            },
+            Event::Key(Key::Char('\n')) => {
+                let i = self.selection_index;
+                self.activate(i)
+            },
+            Event::Key(Key::Char(c)) => match self.menu.shortcut_index(c) {
+                Some(i) => {
+                    self.selection_index = i;
+                    self.activate(i)
+                },
+                // An unrecognized key closes the menu, as the old loop did.
+                None => EventResult::Pop,
+            },
+            Event::Key(Key::Esc) => EventResult::Pop,
+            Event::Mouse(m) => {
+                // Resolve the borrow into an owned decision before touching self.
+                let mut selection = self.selection_index;
+                enum Decision { Hover, Activate, Dismiss, Ignore }
+                let decision = match self.menu.maybe_handle_mouse(m, self.origin, &mut selection) {
+                    MenuMouse::Hovered => Decision::Hover,
+                    MenuMouse::Activated(_) => Decision::Activate,
+                    MenuMouse::Dismiss => Decision::Dismiss,
+                    MenuMouse::Ignored => Decision::Ignore,
+                };
+                self.selection_index = selection;
+                match decision {
+                    Decision::Hover => EventResult::Consumed,
+                    Decision::Activate => self.activate(selection),
+                    Decision::Dismiss => EventResult::Pop,
+                    Decision::Ignore => EventResult::Ignored,
+                }
+            },
+            _ => EventResult::Ignored,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn click_off_the_bar_row_is_ignored() {
+        let mut bar = MenuBar {
+            selection_index: 0,
+            menus: vec![(
+                "_File".to_string(),
+                Menu { children: vec![("_New".to_string(), MenuAction::Action(Action::New))] },
+            )],
+        };
+
+        // The bar lives on row 1. A left click on row 5 (the editor body)
+        // under the "File" span must not open the menu.
+        let click = MouseEvent::Press(MouseButton::Left, 2, 5);
+        assert!(bar.maybe_handle_mouse(click, 1).is_none());
     }
 }
@@ -3,13 +3,67 @@
 //! Rendering graphics is done using high-level functions, that are, by themselves,
 //! unrelated to the backend at hand.
 
-use termion::{color, cursor};
+use termion::{color, cursor, style};
 use vek::*;
 use std::io::Write;
 
 static DEFAULT_FG: Fg = Fg(Color::White);
 static DEFAULT_BG: Bg = Bg(Color::Black);
 
+/// Text rendering attributes, packed as a small bitset. Combine with `|`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Attrs(u8);
+
+impl Attrs {
+    pub const NONE: Attrs = Attrs(0);
+    pub const BOLD: Attrs = Attrs(1 << 0);
+    pub const DIM: Attrs = Attrs(1 << 1);
+    pub const ITALIC: Attrs = Attrs(1 << 2);
+    pub const UNDERLINE: Attrs = Attrs(1 << 3);
+    pub const REVERSE: Attrs = Attrs(1 << 4);
+    pub const STRIKETHROUGH: Attrs = Attrs(1 << 5);
+
+    pub fn contains(self, other: Attrs) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl Default for Attrs {
+    fn default() -> Attrs {
+        Attrs::NONE
+    }
+}
+
+impl std::ops::BitOr for Attrs {
+    type Output = Attrs;
+    fn bitor(self, rhs: Attrs) -> Attrs {
+        Attrs(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::BitOrAssign for Attrs {
+    fn bitor_assign(&mut self, rhs: Attrs) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl std::fmt::Display for Attrs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use termion::style;
+        if self.contains(Attrs::BOLD) { style::Bold.fmt(f)?; }
+        if self.contains(Attrs::DIM) { style::Faint.fmt(f)?; }
+        if self.contains(Attrs::ITALIC) { style::Italic.fmt(f)?; }
+        if self.contains(Attrs::UNDERLINE) { style::Underline.fmt(f)?; }
+        if self.contains(Attrs::REVERSE) { style::Invert.fmt(f)?; }
+        if self.contains(Attrs::STRIKETHROUGH) { style::CrossedOut.fmt(f)?; }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Fg(pub Color);
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -22,6 +76,8 @@ impl std::fmt::Display for Fg {
             Color::Black => color::Fg(color::Black).fmt(f),
             Color::LightWhite => color::Fg(color::LightWhite).fmt(f),
             Color::Blue => color::Fg(color::Blue).fmt(f),
+            Color::Indexed(i) => color::Fg(color::AnsiValue(i)).fmt(f),
+            Color::Rgb(r, g, b) => color::Fg(color::Rgb(r, g, b)).fmt(f),
         }
     }
 }
@@ -33,6 +89,8 @@ impl std::fmt::Display for Bg {
             Color::Black => color::Bg(color::Black).fmt(f),
             Color::LightWhite => color::Bg(color::LightWhite).fmt(f),
             Color::Blue => color::Bg(color::Blue).fmt(f),
+            Color::Indexed(i) => color::Bg(color::AnsiValue(i)).fmt(f),
+            Color::Rgb(r, g, b) => color::Bg(color::Rgb(r, g, b)).fmt(f),
         }
     }
 }
@@ -43,14 +101,128 @@ pub enum Color {
     Black,
     LightWhite,
     Blue,
+    /// A color from the 256-color palette, mapped to termion's `AnsiValue`.
+    Indexed(u8),
+    /// A 24-bit true-color value, mapped to termion's `Rgb`.
+    Rgb(u8, u8, u8),
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
-pub struct Cell(char, Fg, Bg);
+/// The sentinel character stored in the trailing column of a width-2 glyph.
+/// `render_ansi` skips any cell carrying it so no stray character is emitted
+/// for the column the wide glyph already occupies.
+const CONTINUATION: char = '\0';
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cell {
+    /// The primary character occupying this column (or [`CONTINUATION`] if this
+    /// cell is the trailing half of a wide glyph to its left).
+    ch: char,
+    /// Zero-width combining marks rendered on top of `ch`, in the order seen.
+    combining: String,
+    fg: Fg,
+    bg: Bg,
+    attrs: Attrs,
+}
+
+impl Cell {
+    fn new(ch: char, fg: Fg, bg: Bg, attrs: Attrs) -> Cell {
+        Cell { ch, combining: String::new(), fg, bg, attrs }
+    }
+
+    /// The trailing column of a width-2 glyph. Carries the same colors and
+    /// attributes as its leading cell so a bare continuation never forces an
+    /// escape sequence of its own.
+    fn continuation(fg: Fg, bg: Bg, attrs: Attrs) -> Cell {
+        Cell { ch: CONTINUATION, combining: String::new(), fg, bg, attrs }
+    }
+}
 
 impl Default for Cell {
     fn default() -> Cell {
-        Cell(' ', DEFAULT_FG, DEFAULT_BG)
+        Cell::new(' ', DEFAULT_FG, DEFAULT_BG, Attrs::NONE)
+    }
+}
+
+/// Terminal column width of `c`, in the spirit of POSIX `wcwidth`:
+/// 0 for zero-width/combining marks, 2 for wide (CJK/emoji) glyphs, 1 otherwise.
+fn char_width(c: char) -> usize {
+    if is_zero_width(c) {
+        0
+    } else if is_wide(c) {
+        2
+    } else {
+        1
+    }
+}
+
+/// Combining marks and other zero-width code points that fold onto the
+/// preceding cell instead of claiming a column of their own.
+fn is_zero_width(c: char) -> bool {
+    let cp = c as u32;
+    matches!(cp,
+        0x0300..=0x036F // combining diacritical marks
+        | 0x0483..=0x0489
+        | 0x0591..=0x05BD
+        | 0x0610..=0x061A
+        | 0x064B..=0x065F
+        | 0x0670
+        | 0x06D6..=0x06DC
+        | 0x0E31 | 0x0E34..=0x0E3A
+        | 0x1AB0..=0x1AFF // combining diacritical marks extended
+        | 0x1DC0..=0x1DFF // combining diacritical marks supplement
+        | 0x200B..=0x200F // zero-width space / joiners / marks
+        | 0x20D0..=0x20FF // combining marks for symbols
+        | 0xFE00..=0xFE0F // variation selectors
+        | 0xFE20..=0xFE2F // combining half marks
+        | 0xE0100..=0xE01EF // variation selectors supplement
+    )
+}
+
+/// Double-width glyphs per the East Asian Wide/Fullwidth property, plus the
+/// common emoji blocks that render two columns wide.
+fn is_wide(c: char) -> bool {
+    let cp = c as u32;
+    matches!(cp,
+        0x1100..=0x115F // Hangul Jamo
+        | 0x2329 | 0x232A
+        | 0x2E80..=0x303E // CJK radicals .. Kangxi
+        | 0x3041..=0x33FF // Hiragana .. CJK compatibility
+        | 0x3400..=0x4DBF // CJK ext A
+        | 0x4E00..=0x9FFF // CJK unified ideographs
+        | 0xA000..=0xA4CF // Yi
+        | 0xAC00..=0xD7A3 // Hangul syllables
+        | 0xF900..=0xFAFF // CJK compatibility ideographs
+        | 0xFE30..=0xFE4F // CJK compatibility forms
+        | 0xFF00..=0xFF60 // fullwidth forms
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F // emoji & pictographs
+        | 0x1F900..=0x1F9FF // supplemental symbols & pictographs
+        | 0x20000..=0x3FFFD // CJK ext B and beyond
+    )
+}
+
+/// An inclusive rectangular sub-region of a [`Grid`], used to scroll a slice of
+/// the screen (e.g. a gutter-excluded text column) without disturbing the rest.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ScrollRegion {
+    pub top: usize,
+    pub bottom: usize,
+    pub left: usize,
+    pub right: usize,
+}
+
+impl ScrollRegion {
+    /// Construct a region, validating that the corners are the right way round
+    /// so `height()` (and the scroll arithmetic) can never underflow on a
+    /// backwards region.
+    pub fn new(top: usize, bottom: usize, left: usize, right: usize) -> ScrollRegion {
+        assert!(top <= bottom, "ScrollRegion top ({}) must not exceed bottom ({})", top, bottom);
+        assert!(left <= right, "ScrollRegion left ({}) must not exceed right ({})", left, right);
+        ScrollRegion { top, bottom, left, right }
+    }
+
+    fn height(&self) -> usize {
+        self.bottom - self.top + 1
     }
 }
 
@@ -90,7 +262,7 @@ impl Grid {
         match self.idx_of(pos.into()) {
             Some(idx) => self.cells
                 .get(idx)
-                .copied()
+                .cloned()
                 .unwrap_or(Cell::default()),
             None => Cell::default(),
         }
@@ -115,6 +287,68 @@ impl Grid {
             None => {},
         }
     }
+
+    /// Reset every cell in the grid to the default.
+    fn clear(&mut self) {
+        for cell in self.cells.iter_mut() {
+            *cell = Cell::default();
+        }
+    }
+
+    /// Reset every cell inside `region` to the default.
+    fn clear_region(&mut self, region: ScrollRegion) {
+        for row in region.top..=region.bottom {
+            for col in region.left..=region.right {
+                self.set((col, row), Cell::default());
+            }
+        }
+    }
+
+    /// Shift the rows within `region` up by `n`, filling the exposed rows at the
+    /// bottom with defaults. Columns outside `left..=right` are untouched. An
+    /// `n` at least the region height clears the whole region.
+    pub fn scroll_up(&mut self, region: ScrollRegion, n: usize) {
+        if n == 0 { return; }
+        if n >= region.height() {
+            self.clear_region(region);
+            return;
+        }
+        // Destination rows read from higher rows, so ascending order is safe.
+        for row in region.top..=region.bottom - n {
+            for col in region.left..=region.right {
+                let src = self.get((col, row + n));
+                self.set((col, row), src);
+            }
+        }
+        for row in region.bottom - n + 1..=region.bottom {
+            for col in region.left..=region.right {
+                self.set((col, row), Cell::default());
+            }
+        }
+    }
+
+    /// Shift the rows within `region` down by `n`, filling the exposed rows at
+    /// the top with defaults. Columns outside `left..=right` are untouched. An
+    /// `n` at least the region height clears the whole region.
+    pub fn scroll_down(&mut self, region: ScrollRegion, n: usize) {
+        if n == 0 { return; }
+        if n >= region.height() {
+            self.clear_region(region);
+            return;
+        }
+        // Destination rows read from lower rows, so descend to avoid clobbering.
+        for row in (region.top + n..=region.bottom).rev() {
+            for col in region.left..=region.right {
+                let src = self.get((col, row - n));
+                self.set((col, row), src);
+            }
+        }
+        for row in region.top..=region.top + n - 1 {
+            for col in region.left..=region.right {
+                self.set((col, row), Cell::default());
+            }
+        }
+    }
 }
 
 /// When we need to access already rendered cells on the terminal, we require a double buffer.
@@ -130,6 +364,7 @@ pub struct RenderBuffer {
     grids: (Grid, Grid),
     fg:    Fg,
     bg:    Bg,
+    attrs: Attrs,
 }
 
 impl RenderBuffer {
@@ -137,7 +372,7 @@ impl RenderBuffer {
     pub fn new(size: (usize, usize)) -> RenderBuffer {
         let size = Extent2::from(size);
         let grid = Grid::new(size);
-        RenderBuffer { size, grids: (grid.clone(), grid), fg: DEFAULT_FG, bg: DEFAULT_BG }
+        RenderBuffer { size, grids: (grid.clone(), grid), fg: DEFAULT_FG, bg: DEFAULT_BG, attrs: Attrs::NONE }
     }
 
     /// Truncate cells or append new blank cells to the buffer to fit
@@ -156,15 +391,76 @@ impl RenderBuffer {
         self.bg = Bg(bg);
     }
 
+    /// Set the text attributes applied to subsequently drawn cells.
+    pub fn set_attrs(&mut self, attrs: Attrs) {
+        self.attrs = attrs;
+    }
+
+    /// Drop back to unstyled cells for subsequent draws.
+    pub fn clear_attrs(&mut self) {
+        self.attrs = Attrs::NONE;
+    }
+
     #[inline(always)]
     pub fn set_cell(&mut self, pos: impl Into<Vec2<usize>>, ch: char) {
-        self.grids.1.set(pos, Cell(ch, self.fg, self.bg))
+        let pos = pos.into();
+        match char_width(ch) {
+            // A combining mark claims no column; fold it onto the glyph to our
+            // left. Walk past any continuation sentinel so a mark following a
+            // wide glyph lands on the glyph cell, not its trailing column (which
+            // `render_ansi` skips).
+            0 => {
+                let mut x = pos.x;
+                while x > 0 {
+                    x -= 1;
+                    if self.grids.1.get((x, pos.y)).ch != CONTINUATION {
+                        let mut left = self.grids.1.get((x, pos.y));
+                        left.combining.push(ch);
+                        self.grids.1.set((x, pos.y), left);
+                        break;
+                    }
+                }
+            },
+            // A wide glyph owns its cell and plants a continuation sentinel beside it.
+            2 => {
+                self.grids.1.set(pos, Cell::new(ch, self.fg, self.bg, self.attrs));
+                self.grids.1.set((pos.x + 1, pos.y), Cell::continuation(self.fg, self.bg, self.attrs));
+            },
+            _ => self.grids.1.set(pos, Cell::new(ch, self.fg, self.bg, self.attrs)),
+        }
+    }
+
+    /// Scroll a sub-region of the next frame up by `n` rows (see
+    /// [`Grid::scroll_up`]). Only the dirtied rows will differ from the front
+    /// buffer, so `render_ansi` emits a minimal update.
+    pub fn scroll_up(&mut self, region: ScrollRegion, n: usize) {
+        self.grids.1.scroll_up(region, n);
+    }
+
+    /// Scroll a sub-region of the next frame down by `n` rows (see
+    /// [`Grid::scroll_down`]).
+    pub fn scroll_down(&mut self, region: ScrollRegion, n: usize) {
+        self.grids.1.scroll_down(region, n);
+    }
+
+    /// Reset the back buffer to defaults for the next frame. Widgets redraw
+    /// into a blank slate each frame, so a glyph that is no longer drawn (e.g.
+    /// the tail of a shrunken line) differs from the front buffer and gets
+    /// blanked by the next `render_ansi`.
+    pub fn clear(&mut self) {
+        self.grids.1.clear();
     }
 
     pub fn draw(&mut self, origin: (usize, usize), draw: Draw) {
         match draw {
-            Draw::Text(s) => for (i, c) in s.chars().enumerate() {
-                self.set_cell((origin.0 + i, origin.1), c);
+            Draw::Text(s) => {
+                // Advance by the measured column width of each glyph, not its
+                // char index, so wide glyphs and combining marks stay aligned.
+                let mut offset = 0usize;
+                for c in s.chars() {
+                    self.set_cell((origin.0 + offset, origin.1), c);
+                    offset += char_width(c);
+                }
             },
             Draw::Rect(w, h) => for x in 0..w {
                 for y in 0..h {
@@ -181,40 +477,63 @@ impl RenderBuffer {
         let mut last_pos = Vec2::one();
         let mut last_fg = DEFAULT_FG;
         let mut last_bg = DEFAULT_BG;
+        let mut last_attrs = Attrs::NONE;
 
         for row in 0..self.size.h {
             for col in 0..self.size.w {
                 let (front, back) = (self.grids.0.get_mut((col, row)), self.grids.1.get((col, row)));
 
                 if *front != back {
-                    if last_pos != Vec2::new(col.saturating_sub(1), row) { // If this cell didn't follow immediately after the last (cursor optimization)
-                        out.push_str(&format!("{}", cursor::Goto(col as u16 + 1, row as u16 + 1)));
+                    // Continuation cells belong to the wide glyph on their left,
+                    // which already advanced the terminal cursor. Reconcile the
+                    // diff but emit nothing for this column.
+                    if back.ch != CONTINUATION {
+                        if last_pos != Vec2::new(col.saturating_sub(1), row) { // If this cell didn't follow immediately after the last (cursor optimization)
+                            out.push_str(&format!("{}", cursor::Goto(col as u16 + 1, row as u16 + 1)));
+                        }
+
+                        // Color and attributes optimizations. We don't want to write
+                        // an ANSI color value for every character we draw. So we do this to
+                        // minimize the number of ANSI escape sequences we generate.
+                        if last_attrs != back.attrs {
+                            // SGR has no additive way to turn a single attribute
+                            // back off, so whenever one clears we reset everything
+                            // and re-apply. The reset also wipes fg/bg, so emit
+                            // this cell's colors again right after it.
+                            if !Attrs(last_attrs.0 & !back.attrs.0).is_empty() {
+                                out.push_str(&format!("{}{}{}", style::Reset, back.fg, back.bg));
+                                last_fg = back.fg;
+                                last_bg = back.bg;
+                                last_attrs = Attrs::NONE;
+                            }
+                            let added = Attrs(back.attrs.0 & !last_attrs.0);
+                            if !added.is_empty() {
+                                out.push_str(&format!("{}", added));
+                            }
+                            last_attrs = back.attrs;
+                        }
+                        if last_fg != back.fg {
+                            out.push_str(&format!("{}", back.fg));
+                            last_fg = back.fg;
+                        }
+                        if last_bg != back.bg {
+                            out.push_str(&format!("{}", back.bg));
+                            last_bg = back.bg;
+                        }
+                        out.push(back.ch); // Write the character
+                        out.push_str(&back.combining); // ..and any combining marks stacked on it
+
+                        last_pos = Vec2::new(col, row); // Update last position
                     }
 
-                    let Cell(c, fg, bg) = back;
-                    
-                    // Color and attributes optimizations. We don't want to write
-                    // an ANSI color value for every character we draw. So we do this to
-                    // minimize the number of ANSI escape sequences we generate.
-                    if last_fg != fg {
-                        out.push_str(&format!("{}", fg));
-                        last_fg = fg;
-                    }
-                    if last_bg != bg {
-                        out.push_str(&format!("{}", bg));
-                        last_bg = bg;
-                    }
-                    out.push(c); // Write the character
-
                     *front = back; // Copy cells from the current buffer to the other
-
-                    last_pos = Vec2::new(col, row); // Update last position
                 }
             }
         }
 
-        // self.grids.0 = self.grids.1.clone(); // TODO copy when drawing cells above
-        dbg!(&out);
+        // Every differing cell was copied into the front buffer above and the
+        // rest were already equal, so `front == back` now holds everywhere —
+        // the next frame diffs against exactly what is on screen.
         out
     }
 
@@ -232,3 +551,83 @@ pub enum Draw<'a> {
     Text(&'a str),
     Rect(usize, usize),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clear_blanks_the_tail_of_a_shrunken_line() {
+        let mut buf = RenderBuffer::new((10, 1));
+
+        // Draw a line and flush it so the front buffer holds "hello".
+        buf.draw((0, 0), Draw::Text("hello"));
+        let _ = buf.render_ansi();
+
+        // Next frame: clear the back buffer and draw a shorter line.
+        buf.clear();
+        buf.draw((0, 0), Draw::Text("hi"));
+        let out = buf.render_ansi();
+
+        // Columns 2..=4 ("llo") must be overwritten with spaces. They follow
+        // the 'i' contiguously, so no cursor move separates them.
+        assert!(out.contains("i   "), "tail cells not blanked: {:?}", out);
+    }
+
+    #[test]
+    fn combining_mark_after_wide_glyph_attaches_to_the_glyph() {
+        let mut buf = RenderBuffer::new((10, 1));
+
+        // A wide glyph at column 0 (+continuation at column 1) followed by a
+        // combining acute accent, which must fold onto the glyph, not the
+        // skipped continuation cell.
+        buf.draw((0, 0), Draw::Text("\u{8282}\u{0301}"));
+        let out = buf.render_ansi();
+
+        assert!(out.contains('\u{8282}'), "wide glyph missing: {:?}", out);
+        assert!(out.contains('\u{0301}'), "combining mark dropped: {:?}", out);
+    }
+
+    fn column(chars: &[char]) -> Grid {
+        let mut grid = Grid::new(Extent2::new(1, chars.len()));
+        for (y, &c) in chars.iter().enumerate() {
+            grid.set((0, y), Cell::new(c, DEFAULT_FG, DEFAULT_BG, Attrs::NONE));
+        }
+        grid
+    }
+
+    #[test]
+    fn scroll_up_shifts_rows_and_blanks_the_bottom() {
+        let mut grid = column(&['a', 'b', 'c', 'd']);
+        grid.scroll_up(ScrollRegion::new(0, 3, 0, 0), 1);
+        assert_eq!(grid.get((0, 0)).ch, 'b');
+        assert_eq!(grid.get((0, 1)).ch, 'c');
+        assert_eq!(grid.get((0, 2)).ch, 'd');
+        assert_eq!(grid.get((0, 3)).ch, ' ');
+    }
+
+    #[test]
+    fn scroll_down_shifts_rows_and_blanks_the_top() {
+        let mut grid = column(&['a', 'b', 'c', 'd']);
+        grid.scroll_down(ScrollRegion::new(0, 3, 0, 0), 1);
+        assert_eq!(grid.get((0, 0)).ch, ' ');
+        assert_eq!(grid.get((0, 1)).ch, 'a');
+        assert_eq!(grid.get((0, 2)).ch, 'b');
+        assert_eq!(grid.get((0, 3)).ch, 'c');
+    }
+
+    #[test]
+    fn scroll_by_at_least_height_clears_the_whole_region() {
+        let mut grid = column(&['a', 'b', 'c', 'd']);
+        grid.scroll_up(ScrollRegion::new(0, 3, 0, 0), 4);
+        for y in 0..4 {
+            assert_eq!(grid.get((0, y)).ch, ' ');
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn backwards_region_is_rejected() {
+        ScrollRegion::new(3, 0, 0, 0);
+    }
+}